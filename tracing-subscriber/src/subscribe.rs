@@ -7,7 +7,12 @@ use tracing_core::{
 
 #[cfg(feature = "registry")]
 use crate::registry::{self, LookupSpan, Registry, SpanRef};
-use std::{any::TypeId, marker::PhantomData};
+use std::{
+    any::TypeId,
+    cell::Cell,
+    marker::PhantomData,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 /// A composable handler for `tracing` events.
 ///
@@ -298,6 +303,33 @@ where
         true
     }
 
+    /// Called once immediately after this subscriber is composed with the
+    /// given [`Collector`] via [`with_collector`], before any span or event
+    /// is recorded.
+    ///
+    /// This provides a well-defined initialization point for subscribers that
+    /// need to learn something about the collector they have been composed
+    /// with &mdash; for example, to cache a [`max_level_hint`], or to check
+    /// that the collector implements a trait the subscriber requires (such as
+    /// [`LookupSpan`]) &mdash; as opposed to doing so lazily the first time a
+    /// notification method is called.
+    ///
+    /// When a subscriber stack is assembled with multiple calls to
+    /// [`and_then`], `on_register` is called on each subscriber in the stack
+    /// in order, starting with the innermost (the one closest to the
+    /// collector) and ending with the outermost.
+    ///
+    /// By default, this does nothing.
+    ///
+    /// [`Collector`]: https://docs.rs/tracing-core/latest/tracing_core/trait.Subscriber.html
+    /// [`with_collector`]: #method.with_collector
+    /// [`max_level_hint`]: #method.max_level_hint
+    /// [`and_then`]: #method.and_then
+    /// [`LookupSpan`]: ../registry/trait.LookupSpan.html
+    fn on_register(&mut self, collector: &C) {
+        let _ = collector;
+    }
+
     /// Notifies this layer that a new span was constructed with the given
     /// `Attributes` and `Id`.
     fn new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, C>) {
@@ -342,6 +374,20 @@ where
     /// subscriber returned a different ID.
     fn on_id_change(&self, _old: &span::Id, _new: &span::Id, _ctx: Context<'_, C>) {}
 
+    /// Notifies this subscriber that the program is shutting down, so that
+    /// any buffered spans or events should be flushed now.
+    ///
+    /// Subscribers that batch or otherwise buffer their output (such as
+    /// network exporters or file writers with their own internal queues)
+    /// should override this method to drain any pending data. This is
+    /// typically invoked by a guard that is dropped at the end of `main`, via
+    /// [`Layered::flush`].
+    ///
+    /// By default, this does nothing.
+    ///
+    /// [`Layered::flush`]: struct.Layered.html#method.flush
+    fn on_flush(&self) {}
+
     /// Composes this subscriber around the given `Subscriber`, returning a `Layered`
     /// struct implementing `Subscriber`.
     ///
@@ -451,6 +497,64 @@ where
         }
     }
 
+    /// Combines `self` with a [`Filter`], returning a [`Filtered`] subscriber.
+    ///
+    /// The [`Filter`] is used to determine whether this subscriber should be
+    /// notified about a given span or event. Unlike the [`Subscribe::enabled`]
+    /// and [`Subscribe::register_callsite`] methods, whose return values are
+    /// combined with those of every other `Subscribe` in a [`Layered`] stack to
+    /// determine whether a span or event is enabled *globally*, a [`Filter`]
+    /// attached via `with_filter` only affects whether the wrapped subscriber
+    /// itself observes that span or event — sibling subscribers in the same
+    /// stack are unaffected.
+    ///
+    /// For example:
+    /// ```rust
+    /// # use tracing_subscriber::subscribe::{Subscribe, Filter};
+    /// # use tracing_subscriber::prelude::*;
+    /// # use tracing_core::{Collect, Metadata};
+    /// pub struct MySubscriber {
+    ///     // ...
+    /// }
+    ///
+    /// impl<C: Collect> Subscribe<C> for MySubscriber {
+    ///     // ...
+    /// }
+    ///
+    /// pub struct MyFilter {
+    ///     // ...
+    /// }
+    ///
+    /// impl<C: Collect> Filter<C> for MyFilter {
+    ///     fn enabled(&self, metadata: &Metadata<'_>, ctx: &tracing_subscriber::subscribe::Context<'_, C>) -> bool {
+    ///         // ...
+    /// #       drop((metadata, ctx)); true
+    ///     }
+    /// }
+    /// # impl MySubscriber { fn new() -> Self { Self {} } }
+    /// # impl MyFilter { fn new() -> Self { Self {} } }
+    /// # fn docs<C: Collect>() -> tracing_subscriber::subscribe::Filtered<MySubscriber, MyFilter, C> {
+    ///
+    /// // Only `MySubscriber` will respect `MyFilter`'s decision about whether
+    /// // to observe a given span or event; other subscribers in the stack
+    /// // will still see it.
+    /// let subscriber = MySubscriber::new().with_filter(MyFilter::new());
+    /// # subscriber }
+    /// ```
+    ///
+    /// [`Filter`]: trait.Filter.html
+    /// [`Filtered`]: struct.Filtered.html
+    /// [`Subscribe::enabled`]: #method.enabled
+    /// [`Subscribe::register_callsite`]: #method.register_callsite
+    /// [`Layered`]: struct.Layered.html
+    fn with_filter<F>(self, filter: F) -> Filtered<Self, F, C>
+    where
+        Self: Sized,
+        F: Filter<C>,
+    {
+        Filtered::new(self, filter)
+    }
+
     /// Composes this `Subscriber` with the given [`Collector`], returning a
     /// `Layered` struct that implements [`Collector`].
     ///
@@ -494,10 +598,11 @@ where
     ///```
     ///
     /// [`Collector`]: https://docs.rs/tracing-core/latest/tracing_core/trait.Collector.html
-    fn with_collector(self, inner: C) -> Layered<Self, C>
+    fn with_collector(mut self, inner: C) -> Layered<Self, C>
     where
         Self: Sized,
     {
+        self.on_register(&inner);
         Layered {
             subscriber: self,
             inner,
@@ -505,6 +610,55 @@ where
         }
     }
 
+    /// Erases the type of this `Subscribe`, returning a [`Box`]ed `Subscribe`
+    /// trait object.
+    ///
+    /// This can be used when a function conditionally constructs one of
+    /// several different `Subscribe` implementations that would otherwise
+    /// have different concrete types, allowing all of the branches to unify
+    /// on a single `Box<dyn Subscribe<C> + Send + Sync>` type. For example:
+    ///
+    /// ```rust
+    /// use tracing_subscriber::subscribe::Subscribe;
+    /// use tracing_core::Collect;
+    ///
+    /// pub struct FooSubscriber {
+    ///     // ...
+    /// }
+    ///
+    /// pub struct BarSubscriber {
+    ///     // ...
+    /// }
+    ///
+    /// impl<C: Collect> Subscribe<C> for FooSubscriber {
+    ///     // ...
+    /// }
+    ///
+    /// impl<C: Collect> Subscribe<C> for BarSubscriber {
+    ///     // ...
+    /// }
+    /// # impl FooSubscriber { fn new() -> Self { Self {} } }
+    /// # impl BarSubscriber { fn new() -> Self { Self {} } }
+    ///
+    /// # fn docs<C: Collect + Send + Sync + 'static>(use_foo: bool) -> Box<dyn Subscribe<C> + Send + Sync> {
+    /// let subscriber = if use_foo {
+    ///     FooSubscriber::new().boxed()
+    /// } else {
+    ///     BarSubscriber::new().boxed()
+    /// };
+    /// # subscriber }
+    /// ```
+    ///
+    /// [`Box`]: https://doc.rust-lang.org/std/boxed/struct.Box.html
+    fn boxed(self) -> Box<dyn Subscribe<C> + Send + Sync + 'static>
+    where
+        Self: Sized,
+        Self: Subscribe<C> + Send + Sync + 'static,
+        C: Collect,
+    {
+        Box::new(self)
+    }
+
     #[doc(hidden)]
     unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
         if id == TypeId::of::<Self>() {
@@ -589,6 +743,484 @@ pub struct Scope<'a, L: LookupSpan<'a>>(
     Option<std::iter::Chain<registry::FromRoot<'a, L>, std::iter::Once<SpanRef<'a, L>>>>,
 );
 
+/// A per-[`Subscribe`] filter that determines whether a span or event is
+/// enabled *for that subscriber*, without affecting whether other subscribers
+/// in the same [`Layered`] stack observe it.
+///
+/// Unlike [`Subscribe::enabled`] and [`Subscribe::register_callsite`], which
+/// determine whether a span or event is enabled *globally* for an entire
+/// subscriber stack, a `Filter` is attached to a single `Subscribe` using the
+/// [`with_filter`] combinator, and only determines whether *that* subscriber
+/// observes the span or event.
+///
+/// [`Subscribe`]: trait.Subscribe.html
+/// [`Layered`]: struct.Layered.html
+/// [`Subscribe::enabled`]: trait.Subscribe.html#method.enabled
+/// [`Subscribe::register_callsite`]: trait.Subscribe.html#method.register_callsite
+/// [`with_filter`]: trait.Subscribe.html#method.with_filter
+pub trait Filter<C> {
+    /// Returns `true` if this filter would like to observe the given
+    /// [`Metadata`] and [`Context`].
+    ///
+    /// Unlike [`callsite_enabled`], this method is called every time a span
+    /// or event is recorded, and may take the current [`Context`] into
+    /// account.
+    ///
+    /// [`Metadata`]: https://docs.rs/tracing-core/latest/tracing_core/struct.Metadata.html
+    /// [`callsite_enabled`]: #method.callsite_enabled
+    fn enabled(&self, metadata: &Metadata<'_>, cx: &Context<'_, C>) -> bool;
+
+    /// Returns an [`Interest`] indicating whether this filter would like to
+    /// observe the given [`Metadata`] for the lifetime of the callsite.
+    ///
+    /// By default, this returns [`Interest::always()`] if [`self.enabled`]
+    /// returns `true`, or [`Interest::never()`] if it returns `false`.
+    ///
+    /// Whatever this returns, a [`Filtered`] subscriber reports
+    /// [`Interest::sometimes()`] to the rest of the stack, so
+    /// [`Filter::enabled`] is still evaluated for every span or event with
+    /// this callsite, and may take the current [`Context`] into account.
+    ///
+    /// [`Filtered`]: struct.Filtered.html
+    /// [`Metadata`]: https://docs.rs/tracing-core/latest/tracing_core/struct.Metadata.html
+    /// [`Interest`]: https://docs.rs/tracing-core/latest/tracing_core/struct.Interest.html
+    /// [`Interest::always()`]: https://docs.rs/tracing-core/latest/tracing_core/subscriber/struct.Interest.html#method.always
+    /// [`Interest::never()`]: https://docs.rs/tracing-core/latest/tracing_core/subscriber/struct.Interest.html#method.never
+    /// [`Interest::sometimes()`]: https://docs.rs/tracing-core/latest/tracing_core/subscriber/struct.Interest.html#method.sometimes
+    /// [`self.enabled`]: #method.enabled
+    /// [`Filter::enabled`]: #method.enabled
+    fn callsite_enabled(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if self.enabled(metadata, &Context::none()) {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
+    /// Returns an optional hint of the highest [verbosity level][level] that
+    /// this filter will enable.
+    ///
+    /// By default, this returns `None`, indicating that the filter does not
+    /// know its own maximum level.
+    ///
+    /// [level]: https://docs.rs/tracing-core/latest/tracing_core/struct.Level.html
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        None
+    }
+}
+
+/// Uniquely identifies an individual [`Filter`] within a [`Subscribe`] stack.
+///
+/// A `FilterId` is assigned to each [`Filtered`] subscriber when it is
+/// created, and is used to look up that filter's per-callsite or per-span
+/// decision without affecting the behavior of sibling subscribers.
+///
+/// [`Filter`]: trait.Filter.html
+/// [`Subscribe`]: trait.Subscribe.html
+/// [`Filtered`]: struct.Filtered.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub(crate) struct FilterId(u8);
+
+/// The maximum number of [`Filtered`] subscribers supported in a single
+/// process. This is limited by the width of the bitmap used to track which
+/// filters have enabled a given callsite.
+const MAX_FILTERS: usize = 64;
+
+static NEXT_FILTER_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl FilterId {
+    fn next() -> Self {
+        let id = NEXT_FILTER_ID.fetch_add(1, Ordering::Relaxed);
+        assert!(
+            id < MAX_FILTERS,
+            "a maximum of {} `Filtered` subscribers are supported in a single process",
+            MAX_FILTERS,
+        );
+        FilterId(id as u8)
+    }
+
+    fn as_bit(self) -> u64 {
+        1 << self.0
+    }
+}
+
+thread_local! {
+    /// The set of `FilterId`s that most recently enabled the callsite or span
+    /// currently being evaluated on this thread.
+    ///
+    /// Each [`Filtered`] subscriber sets or clears its own bit in this map
+    /// from its `register_callsite`/`enabled` methods, and consults it from
+    /// its notification methods, so that a filter rejecting a span or event
+    /// only suppresses delivery to its own subscriber, rather than
+    /// short-circuiting the whole stack.
+    static FILTERING: Cell<u64> = Cell::new(0);
+}
+
+/// Combines a [`Subscribe`] with a [`Filter`], so that the subscriber is only
+/// notified about spans and events that the filter enables.
+///
+/// This type is returned by [`Subscribe::with_filter`].
+///
+/// [`Subscribe`]: trait.Subscribe.html
+/// [`Filter`]: trait.Filter.html
+/// [`Subscribe::with_filter`]: trait.Subscribe.html#method.with_filter
+#[derive(Clone, Debug)]
+pub struct Filtered<S, F, C> {
+    subscriber: S,
+    filter: F,
+    id: FilterId,
+    _s: PhantomData<fn(C)>,
+}
+
+impl<S, F, C> Filtered<S, F, C> {
+    fn new(subscriber: S, filter: F) -> Self {
+        Self {
+            subscriber,
+            filter,
+            id: FilterId::next(),
+            _s: PhantomData,
+        }
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        FILTERING.with(|filtering| {
+            let bit = self.id.as_bit();
+            let bits = filtering.get();
+            filtering.set(if enabled { bits | bit } else { bits & !bit });
+        });
+    }
+}
+
+/// The set of [`FilterId`]s that enabled a *particular span*, snapshotted
+/// when that span was created and stored in the span's [extensions].
+///
+/// `register_callsite`/`enabled` are only called by `tracing-core`
+/// immediately before a span or event is *created* (because a
+/// [`Filtered`] subscriber always reports [`Interest::sometimes()`], which
+/// forces a fresh `enabled()` call right before `new_span`/`event`
+/// dispatch). They are never called again before `enter`/`exit`/`record`/
+/// `record_follows_from`/`try_close`, which dispatch on the span's `Id`
+/// alone, with no metadata re-evaluation. Reading the `FILTERING`
+/// thread-local directly from those notifications would observe whatever
+/// span or event was *most recently* evaluated on this thread, not the
+/// decision made for the span actually being notified about. A `FilterMap`
+/// fixes this by recording each filter's decision for a span once, at
+/// `new_span` time, so that later notifications can look it back up by
+/// `Id` instead.
+///
+/// [extensions]: ../registry/struct.SpanRef.html#method.extensions
+#[cfg(feature = "registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "registry")))]
+#[derive(Copy, Clone, Debug, Default)]
+struct FilterMap(u64);
+
+#[cfg(feature = "registry")]
+impl FilterMap {
+    fn is_enabled(self, filter: FilterId) -> bool {
+        self.0 & filter.as_bit() != 0
+    }
+
+    fn with(self, filter: FilterId, enabled: bool) -> Self {
+        let bit = filter.as_bit();
+        FilterMap(if enabled { self.0 | bit } else { self.0 & !bit })
+    }
+}
+
+#[cfg(feature = "registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "registry")))]
+impl<S, F, C> Filtered<S, F, C>
+where
+    F: Filter<C>,
+    C: Collect + for<'lookup> LookupSpan<'lookup>,
+{
+    /// Records whether this filter enabled `id` in that span's extensions,
+    /// so that notifications which only carry a `span::Id` (`enter`,
+    /// `exit`, `record`, `record_follows_from`, `try_close`) can look the
+    /// decision back up later, rather than reading the transient
+    /// `FILTERING` thread-local.
+    fn record_enabled_for_span(&self, id: &span::Id, ctx: &Context<'_, C>, enabled: bool) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut extensions = span.extensions_mut();
+        let map = extensions
+            .get_mut::<FilterMap>()
+            .copied()
+            .unwrap_or_default();
+        extensions.insert(map.with(self.id, enabled));
+    }
+
+    /// Returns whether this filter enabled the span identified by `id`, per
+    /// the `FilterMap` recorded for it in [`record_enabled_for_span`].
+    fn is_enabled_for_span(&self, id: &span::Id, ctx: &Context<'_, C>) -> bool {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            // The span has already closed; there is nothing to notify.
+            None => return false,
+        };
+        match span.extensions().get::<FilterMap>() {
+            Some(map) => map.is_enabled(self.id),
+            // No `FilterMap` was recorded for this span (it was likely
+            // created before this `Filtered` subscriber was added to the
+            // stack). Ask the filter directly rather than silently
+            // dropping the notification.
+            None => self.filter.enabled(span.metadata(), ctx),
+        }
+    }
+}
+
+#[cfg(not(feature = "registry"))]
+impl<S, F, C> Filtered<S, F, C>
+where
+    C: Collect,
+{
+    /// Without the `registry` feature, there is nowhere to persist a
+    /// per-span filter decision, so this is a no-op: the `FILTERING`
+    /// thread-local set by `register_callsite`/`enabled` is the only
+    /// decision available, and callers fall back to it via
+    /// [`is_enabled_for_span`].
+    fn record_enabled_for_span(&self, _id: &span::Id, _ctx: &Context<'_, C>, _enabled: bool) {}
+
+    /// Falls back to the live `FILTERING` thread-local, since there is no
+    /// per-span storage available without the `registry` feature. This is
+    /// subject to the same staleness the thread-local has everywhere else:
+    /// it reflects whichever span or event was most recently evaluated on
+    /// this thread, which may not be `id` if anything else was evaluated
+    /// between `id`'s creation and this notification.
+    fn is_enabled_for_span(&self, _id: &span::Id, ctx: &Context<'_, C>) -> bool {
+        ctx.is_enabled_for(self.id)
+    }
+}
+
+// With the `registry` feature enabled, each `Filtered` subscriber's
+// per-span decision is persisted in that span's extensions (see
+// `record_enabled_for_span`/`is_enabled_for_span` above), so `on_enter`,
+// `on_exit`, `on_record`, `on_follows_from`, `on_close`, and
+// `on_id_change` all consult the stored decision for the specific span
+// they were called with, rather than the live `FILTERING` thread-local.
+// `register_callsite`/`enabled`/`new_span`/`on_event` still read/write
+// `FILTERING` directly, since those are always invoked in the same
+// `enabled()` → dispatch pair, with nothing else running in between on
+// the current thread.
+#[cfg(feature = "registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "registry")))]
+impl<S, F, C> Subscribe<C> for Filtered<S, F, C>
+where
+    S: Subscribe<C>,
+    F: Filter<C> + 'static,
+    C: Collect + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_register(&mut self, collector: &C) {
+        self.subscriber.on_register(collector);
+    }
+
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        let interest = self.filter.callsite_enabled(metadata);
+        self.set_enabled(!interest.is_never());
+        // See the identical comment on the `not(feature = "registry")`
+        // impl below: this must stay `sometimes()` so `enabled` (and
+        // `FILTERING`) keep being refreshed for every span and event.
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, C>) -> bool {
+        let enabled = self.filter.enabled(metadata, &ctx);
+        self.set_enabled(enabled);
+        // Always return `true`: whether *this* subscriber is notified is
+        // decided by the bit recorded above and consulted in this
+        // subscriber's notification methods, not by globally disabling the
+        // callsite for the rest of the stack.
+        true
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        self.filter.max_level_hint()
+    }
+
+    fn new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, C>) {
+        // `enabled()` just ran (and set `FILTERING`) immediately before
+        // this call, so the thread-local is still accurate here. Snapshot
+        // that decision into the span's own storage so that later
+        // notifications, which `tracing-core` never re-runs `enabled()`
+        // before, can look it back up by `id` instead of reading
+        // `FILTERING`, which may have moved on to a different span or
+        // event by then.
+        let enabled = ctx.is_enabled_for(self.id);
+        self.record_enabled_for_span(id, &ctx, enabled);
+        if enabled {
+            self.subscriber.new_span(attrs, id, ctx);
+        }
+    }
+
+    fn on_record(&self, span: &span::Id, values: &span::Record<'_>, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(span, &ctx) {
+            self.subscriber.on_record(span, values, ctx);
+        }
+    }
+
+    fn on_follows_from(&self, span: &span::Id, follows: &span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(span, &ctx) {
+            self.subscriber.on_follows_from(span, follows, ctx);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, C>) {
+        if ctx.is_enabled_for(self.id) {
+            self.subscriber.on_event(event, ctx);
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(id, &ctx) {
+            self.subscriber.on_enter(id, ctx);
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(id, &ctx) {
+            self.subscriber.on_exit(id, ctx);
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(&id, &ctx) {
+            self.subscriber.on_close(id, ctx);
+        }
+    }
+
+    fn on_id_change(&self, old: &span::Id, new: &span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(new, &ctx) {
+            self.subscriber.on_id_change(old, new, ctx);
+        }
+    }
+
+    fn on_flush(&self) {
+        self.subscriber.on_flush();
+    }
+
+    #[doc(hidden)]
+    unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+        if id == TypeId::of::<Self>() {
+            Some(self as *const _ as *const ())
+        } else {
+            self.subscriber.downcast_raw(id)
+        }
+    }
+}
+
+// Without the `registry` feature, there is no span storage to persist a
+// per-span filter decision in, so every notification falls back to the
+// live `FILTERING` thread-local, same as before this was fixed for the
+// `registry`-enabled case above. This is known to be racy for
+// `enter`/`exit`/`record`/`record_follows_from`/`try_close`/`on_id_change`
+// whenever something else is evaluated on the same thread between a
+// span's creation and one of those notifications; enable the `registry`
+// feature to get the correct, per-span behavior.
+#[cfg(not(feature = "registry"))]
+impl<S, F, C> Subscribe<C> for Filtered<S, F, C>
+where
+    S: Subscribe<C>,
+    F: Filter<C> + 'static,
+    C: Collect,
+{
+    fn on_register(&mut self, collector: &C) {
+        self.subscriber.on_register(collector);
+    }
+
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        let interest = self.filter.callsite_enabled(metadata);
+        self.set_enabled(!interest.is_never());
+        // Never report `Interest::never()` *or* `Interest::always()` to the
+        // rest of the stack: `tracing-core` caches either of those verdicts
+        // globally, per callsite, across every thread, and never calls
+        // `register_callsite`/`enabled` for that callsite again. Since the
+        // `FILTERING` bit this filter sets is a `thread_local!`, only the
+        // thread that happened to trigger the one-time registration would
+        // ever have it set; every other thread's copy would stay zeroed for
+        // the lifetime of the process. Always report `Interest::sometimes()`
+        // instead, so that `enabled` keeps being re-evaluated (and this
+        // subscriber's bit kept up to date) on every thread, for every span
+        // and event.
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, C>) -> bool {
+        let enabled = self.filter.enabled(metadata, &ctx);
+        self.set_enabled(enabled);
+        // Always return `true`: whether *this* subscriber is notified is
+        // decided by the bit recorded above and consulted in this
+        // subscriber's notification methods, not by globally disabling the
+        // callsite for the rest of the stack.
+        true
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        self.filter.max_level_hint()
+    }
+
+    fn new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(id, &ctx) {
+            self.subscriber.new_span(attrs, id, ctx);
+        }
+    }
+
+    fn on_record(&self, span: &span::Id, values: &span::Record<'_>, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(span, &ctx) {
+            self.subscriber.on_record(span, values, ctx);
+        }
+    }
+
+    fn on_follows_from(&self, span: &span::Id, follows: &span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(span, &ctx) {
+            self.subscriber.on_follows_from(span, follows, ctx);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, C>) {
+        if ctx.is_enabled_for(self.id) {
+            self.subscriber.on_event(event, ctx);
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(id, &ctx) {
+            self.subscriber.on_enter(id, ctx);
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(id, &ctx) {
+            self.subscriber.on_exit(id, ctx);
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(&id, &ctx) {
+            self.subscriber.on_close(id, ctx);
+        }
+    }
+
+    fn on_id_change(&self, old: &span::Id, new: &span::Id, ctx: Context<'_, C>) {
+        if self.is_enabled_for_span(new, &ctx) {
+            self.subscriber.on_id_change(old, new, ctx);
+        }
+    }
+
+    fn on_flush(&self) {
+        self.subscriber.on_flush();
+    }
+
+    #[doc(hidden)]
+    unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+        if id == TypeId::of::<Self>() {
+            Some(self as *const _ as *const ())
+        } else {
+            self.subscriber.downcast_raw(id)
+        }
+    }
+}
+
 // === impl Layered ===
 
 impl<S, C> Collect for Layered<S, C>
@@ -722,6 +1354,13 @@ where
     B: Subscribe<C>,
     C: Collect,
 {
+    fn on_register(&mut self, collector: &C) {
+        // Call `on_register` innermost-first, so that the subscriber closest
+        // to the collector is initialized before the ones layered on top of it.
+        self.inner.on_register(collector);
+        self.subscriber.on_register(collector);
+    }
+
     fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
         let outer = self.subscriber.register_callsite(metadata);
         if outer.is_never() {
@@ -744,7 +1383,7 @@ where
     fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, C>) -> bool {
         if self.subscriber.enabled(metadata, ctx.clone()) {
             // if the outer subscriber enables the callsite metadata, ask the inner subscriber.
-            self.subscriber.enabled(metadata, ctx)
+            self.inner.enabled(metadata, ctx)
         } else {
             // otherwise, the callsite is disabled by this subscriber
             false
@@ -799,6 +1438,15 @@ where
         self.subscriber.on_id_change(old, new, ctx);
     }
 
+    #[inline]
+    fn on_flush(&self) {
+        // Flush the innermost subscriber first, so that subscribers which
+        // depend on state set up by subscribers further down the stack are
+        // flushed after that state has already been drained.
+        self.inner.on_flush();
+        self.subscriber.on_flush();
+    }
+
     #[doc(hidden)]
     unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
         if id == TypeId::of::<Self>() {
@@ -815,6 +1463,13 @@ where
     S: Subscribe<C>,
     C: Collect,
 {
+    #[inline]
+    fn on_register(&mut self, collector: &C) {
+        if let Some(ref mut inner) = self {
+            inner.on_register(collector);
+        }
+    }
+
     #[inline]
     fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
         match self {
@@ -895,6 +1550,13 @@ where
         }
     }
 
+    #[inline]
+    fn on_flush(&self) {
+        if let Some(ref inner) = self {
+            inner.on_flush();
+        }
+    }
+
     #[doc(hidden)]
     #[inline]
     unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
@@ -906,6 +1568,222 @@ where
     }
 }
 
+impl<C> Subscribe<C> for Box<dyn Subscribe<C> + Send + Sync + 'static>
+where
+    C: Collect,
+{
+    #[inline]
+    fn on_register(&mut self, collector: &C) {
+        self.as_mut().on_register(collector)
+    }
+
+    #[inline]
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        self.as_ref().register_callsite(metadata)
+    }
+
+    #[inline]
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, C>) -> bool {
+        self.as_ref().enabled(metadata, ctx)
+    }
+
+    #[inline]
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        self.as_ref().max_level_hint()
+    }
+
+    #[inline]
+    fn new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, C>) {
+        self.as_ref().new_span(attrs, id, ctx)
+    }
+
+    #[inline]
+    fn on_record(&self, span: &span::Id, values: &span::Record<'_>, ctx: Context<'_, C>) {
+        self.as_ref().on_record(span, values, ctx)
+    }
+
+    #[inline]
+    fn on_follows_from(&self, span: &span::Id, follows: &span::Id, ctx: Context<'_, C>) {
+        self.as_ref().on_follows_from(span, follows, ctx)
+    }
+
+    #[inline]
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, C>) {
+        self.as_ref().on_event(event, ctx)
+    }
+
+    #[inline]
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, C>) {
+        self.as_ref().on_enter(id, ctx)
+    }
+
+    #[inline]
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, C>) {
+        self.as_ref().on_exit(id, ctx)
+    }
+
+    #[inline]
+    fn on_close(&self, id: span::Id, ctx: Context<'_, C>) {
+        self.as_ref().on_close(id, ctx)
+    }
+
+    #[inline]
+    fn on_id_change(&self, old: &span::Id, new: &span::Id, ctx: Context<'_, C>) {
+        self.as_ref().on_id_change(old, new, ctx)
+    }
+
+    #[inline]
+    fn on_flush(&self) {
+        self.as_ref().on_flush()
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+        if id == TypeId::of::<Self>() {
+            Some(self as *const _ as *const ())
+        } else {
+            self.as_ref().downcast_raw(id)
+        }
+    }
+}
+
+// Implements `Subscribe` for a runtime-determined collection of boxed
+// subscribers, fanning every notification out to each element in order. This
+// allows composing an arbitrary, runtime-determined number of subscribers
+// (e.g. for a plugin system or a configuration-driven pipeline), where the
+// fixed-arity `and_then` combinator is not sufficient.
+macro_rules! impl_subscribe_for_subscribers {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl<C> Subscribe<C> for $ty
+            where
+                C: Collect,
+            {
+                fn on_register(&mut self, collector: &C) {
+                    for subscriber in self.iter_mut() {
+                        subscriber.on_register(collector);
+                    }
+                }
+
+                fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+                    // The combined interest is the most permissive of every
+                    // element's interest: `never` only if *every* element is
+                    // `never`, `sometimes` if *any* element is `sometimes`
+                    // (so that `enabled` is re-evaluated for every span and
+                    // event), and `always` otherwise.
+                    //
+                    // Every element's `register_callsite` must be called
+                    // unconditionally (no early return), since elements such
+                    // as a `Filtered` subscriber rely on this call to record
+                    // their own per-subscriber interest, even when a
+                    // preceding element already reported `always`.
+                    let mut interest = Interest::never();
+                    for subscriber in self.iter() {
+                        let subscriber_interest = subscriber.register_callsite(metadata);
+                        if subscriber_interest.is_sometimes() {
+                            // `sometimes` always wins, regardless of what any
+                            // other element reported: if even one element
+                            // needs to be re-evaluated for every span and
+                            // event, the combined interest must stay
+                            // `sometimes`, or tracing-core will cache an
+                            // `always` verdict and never call us again.
+                            interest = subscriber_interest;
+                        } else if subscriber_interest.is_always() && !interest.is_sometimes() {
+                            interest = subscriber_interest;
+                        }
+                    }
+                    interest
+                }
+
+                fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, C>) -> bool {
+                    // Every element's `enabled` must be called unconditionally
+                    // (no short-circuiting `any`), since elements such as a
+                    // `Filtered` subscriber rely on this call to record their
+                    // own per-subscriber enabled bit, even when a preceding
+                    // element already returned `true`.
+                    let mut any_enabled = false;
+                    for subscriber in self.iter() {
+                        any_enabled |= subscriber.enabled(metadata, ctx.clone());
+                    }
+                    any_enabled
+                }
+
+                fn max_level_hint(&self) -> Option<LevelFilter> {
+                    self.iter().filter_map(|subscriber| subscriber.max_level_hint()).max()
+                }
+
+                fn new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, C>) {
+                    for subscriber in self.iter() {
+                        subscriber.new_span(attrs, id, ctx.clone());
+                    }
+                }
+
+                fn on_record(&self, span: &span::Id, values: &span::Record<'_>, ctx: Context<'_, C>) {
+                    for subscriber in self.iter() {
+                        subscriber.on_record(span, values, ctx.clone());
+                    }
+                }
+
+                fn on_follows_from(&self, span: &span::Id, follows: &span::Id, ctx: Context<'_, C>) {
+                    for subscriber in self.iter() {
+                        subscriber.on_follows_from(span, follows, ctx.clone());
+                    }
+                }
+
+                fn on_event(&self, event: &Event<'_>, ctx: Context<'_, C>) {
+                    for subscriber in self.iter() {
+                        subscriber.on_event(event, ctx.clone());
+                    }
+                }
+
+                fn on_enter(&self, id: &span::Id, ctx: Context<'_, C>) {
+                    for subscriber in self.iter() {
+                        subscriber.on_enter(id, ctx.clone());
+                    }
+                }
+
+                fn on_exit(&self, id: &span::Id, ctx: Context<'_, C>) {
+                    for subscriber in self.iter() {
+                        subscriber.on_exit(id, ctx.clone());
+                    }
+                }
+
+                fn on_close(&self, id: span::Id, ctx: Context<'_, C>) {
+                    for subscriber in self.iter() {
+                        subscriber.on_close(id.clone(), ctx.clone());
+                    }
+                }
+
+                fn on_id_change(&self, old: &span::Id, new: &span::Id, ctx: Context<'_, C>) {
+                    for subscriber in self.iter() {
+                        subscriber.on_id_change(old, new, ctx.clone());
+                    }
+                }
+
+                fn on_flush(&self) {
+                    for subscriber in self.iter() {
+                        subscriber.on_flush();
+                    }
+                }
+
+                #[doc(hidden)]
+                unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+                    if id == TypeId::of::<Self>() {
+                        return Some(self as *const _ as *const ());
+                    }
+                    self.iter().find_map(|subscriber| subscriber.downcast_raw(id))
+                }
+            }
+        )*
+    };
+}
+
+impl_subscribe_for_subscribers! {
+    Vec<Box<dyn Subscribe<C> + Send + Sync + 'static>>,
+    Box<[Box<dyn Subscribe<C> + Send + Sync + 'static>]>,
+}
+
 #[cfg(feature = "registry")]
 #[cfg_attr(docsrs, doc(cfg(feature = "registry")))]
 impl<'a, S, C> LookupSpan<'a> for Layered<S, C>
@@ -919,6 +1797,39 @@ where
     }
 }
 
+impl<S, C> Layered<S, C>
+where
+    S: Subscribe<C>,
+    C: Collect,
+{
+    /// Notifies this collector's [`Subscribe`] that the program is shutting
+    /// down, so that any buffered spans or events can be flushed before the
+    /// process exits.
+    ///
+    /// This is typically called by a guard type that is dropped at the end
+    /// of `main`, to ensure that any subscribers which batch their output
+    /// (such as network exporters) have a chance to drain their buffers.
+    ///
+    /// This calls [`Subscribe::on_flush`] on this stack's subscriber, which,
+    /// for a stack assembled with multiple [`and_then`] calls, recurses
+    /// through every subscriber in the stack, innermost first.
+    ///
+    /// The wrapped collector is not flushed. If it is itself a `Layered`
+    /// collector (as when a stack is built with repeated
+    /// [`CollectorExt::with`] calls), the subscribers inside it do not
+    /// receive [`Subscribe::on_flush`] from this method.
+    ///
+    /// [`Subscribe`]: trait.Subscribe.html
+    /// [`Subscribe::on_flush`]: trait.Subscribe.html#method.on_flush
+    /// [`and_then`]: trait.Subscribe.html#method.and_then
+    /// [`CollectorExt::with`]: trait.CollectorExt.html#method.with
+    pub fn flush(&self) {
+        // `Collect` has no flush method, so the wrapped collector is not
+        // flushed, even when it is itself a `Layered` collector.
+        self.subscriber.on_flush();
+    }
+}
+
 impl<S, C> Layered<S, C>
 where
     C: Collect,
@@ -970,6 +1881,30 @@ where
             .unwrap_or(true)
     }
 
+    /// Returns `true` if the [`Filter`] identified by `filter` most recently
+    /// decided that it is interested in the span or event currently being
+    /// recorded on this thread.
+    ///
+    /// This is used by [`Filtered`] to determine whether *it specifically*
+    /// should be notified about a span or event, without being affected by
+    /// whether sibling subscribers' filters enabled or disabled it.
+    ///
+    /// Note that this only reflects the *most recent* `register_callsite`/
+    /// `enabled` call on this thread, which `tracing-core` only makes
+    /// immediately before creating a span or event. [`Filtered`] only reads
+    /// this directly from `new_span`/`on_event`, which always run in that
+    /// same immediate pairing; every other notification (`enter`, `exit`,
+    /// `record`, `record_follows_from`, `try_close`, `on_id_change`) looks
+    /// up a per-span decision instead, falling back to this thread-local
+    /// only when the `registry` feature isn't enabled.
+    ///
+    /// [`Filter`]: trait.Filter.html
+    /// [`Filtered`]: struct.Filtered.html
+    #[inline]
+    pub(crate) fn is_enabled_for(&self, filter: FilterId) -> bool {
+        FILTERING.with(|filtering| filtering.get() & filter.as_bit() != 0)
+    }
+
     /// Records the provided `event` with the wrapped collector.
     ///
     /// # Notes
@@ -1126,6 +2061,61 @@ where
         });
         Scope(scope)
     }
+
+    /// Returns an iterator over the [stored data] for all the spans in the
+    /// trace tree containing the span with the given `id`, starting at the
+    /// root of the tree and ending with that span.
+    ///
+    /// Unlike [`scope`], which always starts from the wrapped collector's
+    /// notion of the *current* span, this permits walking the ancestors of
+    /// an arbitrary span &mdash; which is useful when handling a span whose
+    /// explicit parent (set, for example, via `#[instrument(parent = ...)]`)
+    /// differs from the thread's contextual current span.
+    ///
+    /// If this returns `None`, then no span exists for that `id` (either it
+    /// has closed, or the ID is invalid).
+    ///
+    /// [stored data]: ../registry/struct.SpanRef.html
+    /// [`scope`]: #method.scope
+    #[cfg(feature = "registry")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "registry")))]
+    pub fn span_scope(&self, id: &span::Id) -> Option<Scope<'_, C>>
+    where
+        C: for<'lookup> registry::LookupSpan<'lookup>,
+    {
+        let span = self.span(id)?;
+        let parents = span.from_root();
+        Some(Scope(Some(parents.chain(std::iter::once(span)))))
+    }
+
+    /// Returns an iterator over the [stored data] for all the spans that are
+    /// ancestors of the given `event`, starting at the root of the trace tree
+    /// and ending with the event's parent span.
+    ///
+    /// If `event` has an explicit parent (set via
+    /// `event!(parent: ...)` or `Event::child_of`), this starts from
+    /// that span, rather than the wrapped collector's notion of the current
+    /// span. If `event` has no explicit parent, this falls back to the
+    /// contextual current span, behaving like [`scope`].
+    ///
+    /// If this returns `None`, then there are no spans in `event`'s context.
+    ///
+    /// [stored data]: ../registry/struct.SpanRef.html
+    /// [`scope`]: #method.scope
+    #[cfg(feature = "registry")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "registry")))]
+    pub fn event_scope(&self, event: &Event<'_>) -> Option<Scope<'_, C>>
+    where
+        C: for<'lookup> registry::LookupSpan<'lookup>,
+    {
+        if event.is_root() {
+            None
+        } else if let Some(id) = event.parent() {
+            self.span_scope(id)
+        } else {
+            Some(self.scope())
+        }
+    }
 }
 
 impl<'a, C> Context<'a, C> {
@@ -1159,6 +2149,12 @@ impl Identity {
 
 // === impl Scope ===
 
+// This iterator always yields spans root-first: the first item is the root
+// of the trace tree, and the last item is the span the `Scope` was created
+// from (e.g. the current span, for `Context::scope`). Callers that want the
+// opposite, leaf-to-root ordering can call [`Iterator::rev`] on a `Scope`
+// without incurring the heap allocation required to collect and reverse it
+// manually, since `Scope` also implements [`DoubleEndedIterator`].
 #[cfg(feature = "registry")]
 #[cfg_attr(docsrs, doc(cfg(feature = "registry")))]
 impl<'a, L: LookupSpan<'a>> Iterator for Scope<'a, L> {
@@ -1170,6 +2166,18 @@ impl<'a, L: LookupSpan<'a>> Iterator for Scope<'a, L> {
     }
 }
 
+#[cfg(feature = "registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "registry")))]
+impl<'a, L: LookupSpan<'a>> DoubleEndedIterator for Scope<'a, L>
+where
+    registry::FromRoot<'a, L>: DoubleEndedIterator<Item = SpanRef<'a, L>>,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.as_mut()?.next_back()
+    }
+}
+
 #[cfg(feature = "registry")]
 #[cfg_attr(docsrs, doc(cfg(feature = "registry")))]
 impl<'a, L: LookupSpan<'a>> std::fmt::Debug for Scope<'a, L> {
@@ -1296,4 +2304,435 @@ pub(crate) mod tests {
             Collect::downcast_ref::<StringSubscriber3>(&s).expect("subscriber 3 should downcast");
         assert_eq!(&layer.0, "subscriber_3");
     }
-}
\ No newline at end of file
+
+    // === tests for per-subscriber filtering, `on_register`/`on_flush`
+    // ordering, and `Vec`/boxed-slice composition ===
+
+    use std::sync::{atomic::AtomicBool, Arc, Mutex};
+
+    struct TestCallsite;
+
+    impl tracing_core::callsite::Callsite for TestCallsite {
+        fn set_interest(&self, _: Interest) {}
+        fn metadata(&self) -> &Metadata<'_> {
+            unimplemented!("not used by these tests")
+        }
+    }
+
+    static TEST_CALLSITE: TestCallsite = TestCallsite;
+
+    static TEST_METADATA: Metadata<'static> = tracing_core::metadata! {
+        name: "test_event",
+        target: "test_target",
+        level: tracing_core::Level::TRACE,
+        fields: &[],
+        callsite: &TEST_CALLSITE,
+        kind: tracing_core::metadata::Kind::EVENT,
+    };
+
+    fn test_metadata() -> &'static Metadata<'static> {
+        &TEST_METADATA
+    }
+
+    /// A subscriber that records every `on_event` call it receives, so tests
+    /// can assert on exactly which subscribers a given event was delivered
+    /// to.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Recorder {
+        fn events(&self) -> Vec<&'static str> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl<C: Collect> Subscribe<C> for Recorder {
+        fn on_event(&self, _event: &Event<'_>, _ctx: Context<'_, C>) {
+            self.0.lock().unwrap().push("event");
+        }
+    }
+
+    /// A [`Filter`] whose verdict can be flipped at runtime, so tests can
+    /// exercise both the "enabled" and "disabled" paths for a single
+    /// `Filtered` subscriber.
+    #[derive(Clone)]
+    struct ToggleFilter(Arc<AtomicBool>);
+
+    impl ToggleFilter {
+        fn new(enabled: bool) -> Self {
+            Self(Arc::new(AtomicBool::new(enabled)))
+        }
+
+        fn set(&self, enabled: bool) {
+            self.0.store(enabled, Ordering::SeqCst);
+        }
+    }
+
+    impl<C> Filter<C> for ToggleFilter {
+        fn enabled(&self, _metadata: &Metadata<'_>, _cx: &Context<'_, C>) -> bool {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    // These three tests compose `Filtered` with a plain `NopCollector`,
+    // which doesn't implement `LookupSpan`. With the `registry` feature
+    // enabled, `Filtered`'s `Subscribe` impl requires `C: LookupSpan` (see
+    // the per-span `FilterMap` storage above), so they only run against the
+    // `registry`-less fallback. The `registry`-enabled test below covers a
+    // different scenario: `on_enter`/`on_exit` of a `Filtered` subscriber
+    // after an intervening event, against a real `Registry`.
+    #[cfg(not(feature = "registry"))]
+    #[test]
+    fn filtered_does_not_affect_sibling_subscribers() {
+        let sibling = Recorder::default();
+        let filtered_inner = Recorder::default();
+        let toggle = ToggleFilter::new(true);
+        let filtered = filtered_inner.clone().with_filter(toggle.clone());
+
+        let subscribers: Vec<Box<dyn Subscribe<NopCollector> + Send + Sync>> =
+            vec![sibling.clone().boxed(), filtered.boxed()];
+
+        let metadata = test_metadata();
+        let values = metadata.fields().value_set_all(&[]);
+        let event = Event::new_child_of(None, metadata, &values);
+
+        // The filter is enabled: both the sibling and the filtered
+        // subscriber should see the event.
+        assert!(subscribers.enabled(metadata, Context::none()));
+        subscribers.on_event(&event, Context::none());
+        assert_eq!(sibling.events(), vec!["event"]);
+        assert_eq!(filtered_inner.events(), vec!["event"]);
+
+        // The filter now rejects the callsite. The sibling, which has no
+        // filter of its own, must still see the event; only the filtered
+        // subscriber should be skipped.
+        toggle.set(false);
+        assert!(subscribers.enabled(metadata, Context::none()));
+        subscribers.on_event(&event, Context::none());
+        assert_eq!(sibling.events(), vec!["event", "event"]);
+        assert_eq!(filtered_inner.events(), vec!["event"]);
+    }
+
+    #[cfg(not(feature = "registry"))]
+    #[test]
+    fn layered_and_then_enabled_reaches_inner_subscriber() {
+        let sibling = Recorder::default();
+        let filtered_inner = Recorder::default();
+        let toggle = ToggleFilter::new(true);
+        let filtered = filtered_inner.clone().with_filter(toggle.clone());
+
+        // `and_then`'s receiver becomes the new `inner`, so this chains the
+        // filtered subscriber as `inner` and the plain sibling as the outer
+        // `subscriber`, matching the
+        // `some_sub.with_filter(f).and_then(other_sub)` composition that
+        // `Layered`'s `enabled()` must also support, not just `Vec`/
+        // boxed-slice composition.
+        let layered = filtered.and_then(sibling.clone());
+
+        let metadata = test_metadata();
+        let values = metadata.fields().value_set_all(&[]);
+        let event = Event::new_child_of(None, metadata, &values);
+
+        // The filter is enabled: both the sibling and the filtered
+        // subscriber should see the event.
+        assert!(Subscribe::<NopCollector>::enabled(
+            &layered,
+            metadata,
+            Context::none()
+        ));
+        layered.on_event(&event, Context::none());
+        assert_eq!(sibling.events(), vec!["event"]);
+        assert_eq!(filtered_inner.events(), vec!["event"]);
+
+        // The filter now rejects the callsite. `enabled()` must still
+        // consult the inner `Filtered` subscriber (not just call the outer
+        // `subscriber` twice), or its `set_enabled` bit is never refreshed
+        // and the filtered subscriber silently stops firing forever.
+        toggle.set(false);
+        assert!(Subscribe::<NopCollector>::enabled(
+            &layered,
+            metadata,
+            Context::none()
+        ));
+        layered.on_event(&event, Context::none());
+        assert_eq!(sibling.events(), vec!["event", "event"]);
+        assert_eq!(filtered_inner.events(), vec!["event"]);
+    }
+
+    #[cfg(not(feature = "registry"))]
+    #[test]
+    fn vec_subscribers_register_callsite_is_most_permissive() {
+        let sibling = Recorder::default();
+        let filtered = Recorder::default().with_filter(ToggleFilter::new(true));
+
+        let subscribers: Vec<Box<dyn Subscribe<NopCollector> + Send + Sync>> =
+            vec![sibling.boxed(), filtered.boxed()];
+
+        // `Recorder` has no filter and so reports `Interest::always()` by
+        // default, while `Filtered` always downgrades to `sometimes()`. The
+        // combined interest must be `sometimes`, so that the `Filtered`
+        // element keeps being re-evaluated, rather than `always`, which
+        // would let `sibling`'s `always()` verdict suppress that
+        // re-evaluation for every thread for the lifetime of the callsite.
+        let interest = subscribers.register_callsite(test_metadata());
+        assert!(interest.is_sometimes());
+    }
+
+    #[derive(Clone, Default)]
+    struct Tracker(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Tracker {
+        fn record(&self, what: &'static str) {
+            self.0.lock().unwrap().push(what);
+        }
+
+        fn events(&self) -> Vec<&'static str> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    struct TrackingSubscriber {
+        name: &'static str,
+        tracker: Tracker,
+    }
+
+    impl<C: Collect> Subscribe<C> for TrackingSubscriber {
+        fn on_register(&mut self, _collector: &C) {
+            self.tracker.record(self.name);
+        }
+
+        fn on_flush(&self) {
+            self.tracker.record(self.name);
+        }
+    }
+
+    #[test]
+    fn on_register_runs_innermost_first() {
+        let tracker = Tracker::default();
+        // `and_then`'s receiver becomes the new `inner`, and its argument
+        // becomes the new outer `subscriber`, so `"first"` ends up closest
+        // to the collector and must be registered before `"second"`.
+        let _ = TrackingSubscriber {
+            name: "first",
+            tracker: tracker.clone(),
+        }
+        .and_then(TrackingSubscriber {
+            name: "second",
+            tracker: tracker.clone(),
+        })
+        .with_collector(NopCollector);
+
+        assert_eq!(tracker.events(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn layered_flush_reaches_every_subscriber() {
+        let tracker = Tracker::default();
+        let collector = TrackingSubscriber {
+            name: "first",
+            tracker: tracker.clone(),
+        }
+        .and_then(TrackingSubscriber {
+            name: "second",
+            tracker: tracker.clone(),
+        })
+        .with_collector(NopCollector);
+
+        // `on_register` already pushed both names; clear them so this test
+        // only asserts on what `flush` does.
+        tracker.0.lock().unwrap().clear();
+
+        collector.flush();
+        assert_eq!(tracker.events(), vec!["first", "second"]);
+    }
+
+    // === tests for `Context::span_scope`/`event_scope` and
+    // `Scope`'s `DoubleEndedIterator` impl, against a real `Registry` ===
+
+    #[cfg(feature = "registry")]
+    #[derive(Clone, Default)]
+    struct ScopeNames(Arc<Mutex<Option<Vec<&'static str>>>>);
+
+    #[cfg(feature = "registry")]
+    impl ScopeNames {
+        fn set(&self, names: Option<Vec<&'static str>>) {
+            *self.0.lock().unwrap() = names;
+        }
+
+        fn get(&self) -> Option<Vec<&'static str>> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    #[cfg(feature = "registry")]
+    struct RecordEventScope {
+        root_to_leaf: ScopeNames,
+        leaf_to_root: ScopeNames,
+    }
+
+    #[cfg(feature = "registry")]
+    impl<C> Subscribe<C> for RecordEventScope
+    where
+        C: Collect + for<'lookup> LookupSpan<'lookup>,
+    {
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, C>) {
+            match ctx.event_scope(event) {
+                Some(scope) => {
+                    self.root_to_leaf
+                        .set(Some(scope.map(|span| span.name()).collect()));
+                    let scope = ctx.event_scope(event).expect("event_scope is consistent");
+                    self.leaf_to_root
+                        .set(Some(scope.rev().map(|span| span.name()).collect()));
+                }
+                None => {
+                    self.root_to_leaf.set(None);
+                    self.leaf_to_root.set(None);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "registry")]
+    #[test]
+    fn event_scope_root_explicit_parent_and_contextual() {
+        let root_to_leaf = ScopeNames::default();
+        let leaf_to_root = ScopeNames::default();
+        let collector = RecordEventScope {
+            root_to_leaf: root_to_leaf.clone(),
+            leaf_to_root: leaf_to_root.clone(),
+        }
+        .with_collector(Registry::default());
+
+        tracing::collect::with_default(collector, || {
+            // A root event has no ancestors at all, regardless of whether a
+            // span happens to be entered.
+            let _root_span = tracing::info_span!("unrelated").entered();
+            tracing::event!(parent: None, tracing::Level::INFO, "root event");
+            assert_eq!(root_to_leaf.get(), None);
+
+            drop(_root_span);
+
+            // An event with an explicit parent walks from that parent, not
+            // from whatever span is contextually current.
+            let grandparent = tracing::info_span!("grandparent");
+            let parent = {
+                let _grandparent = grandparent.enter();
+                tracing::info_span!("parent")
+            };
+            let _unrelated = tracing::info_span!("unrelated_current").entered();
+            tracing::event!(parent: &parent, tracing::Level::INFO, "explicit parent event");
+            assert_eq!(root_to_leaf.get(), Some(vec!["grandparent", "parent"]));
+            assert_eq!(leaf_to_root.get(), Some(vec!["parent", "grandparent"]));
+            drop(_unrelated);
+
+            // An event with no explicit parent falls back to the
+            // contextual current span.
+            let _contextual = parent.enter();
+            tracing::info!("contextual event");
+            assert_eq!(root_to_leaf.get(), Some(vec!["grandparent", "parent"]));
+            assert_eq!(leaf_to_root.get(), Some(vec!["parent", "grandparent"]));
+        });
+    }
+
+    #[cfg(feature = "registry")]
+    struct RecordSpanScope {
+        names: ScopeNames,
+    }
+
+    #[cfg(feature = "registry")]
+    impl<C> Subscribe<C> for RecordSpanScope
+    where
+        C: Collect + for<'lookup> LookupSpan<'lookup>,
+    {
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, C>) {
+            let id = event
+                .parent()
+                .expect("test events always have an explicit parent");
+            let scope = ctx
+                .span_scope(id)
+                .expect("span_scope should find the explicit parent's ancestry");
+            self.names.set(Some(scope.map(|span| span.name()).collect()));
+        }
+    }
+
+    #[cfg(feature = "registry")]
+    #[test]
+    fn span_scope_follows_explicit_parent_not_current_span() {
+        let names = ScopeNames::default();
+        let collector = RecordSpanScope {
+            names: names.clone(),
+        }
+        .with_collector(Registry::default());
+
+        tracing::collect::with_default(collector, || {
+            let root = tracing::info_span!("root");
+            let explicit_parent = {
+                let _root = root.enter();
+                tracing::info_span!("explicit_parent")
+            };
+
+            // Enter an unrelated span so the thread's contextual current
+            // span differs from `explicit_parent`; `span_scope` must still
+            // walk `explicit_parent`'s own ancestry.
+            let _current = tracing::info_span!("unrelated_current").entered();
+            tracing::event!(parent: &explicit_parent, tracing::Level::INFO, "event");
+        });
+
+        assert_eq!(names.get(), Some(vec!["root", "explicit_parent"]));
+    }
+
+    #[cfg(feature = "registry")]
+    #[derive(Clone, Default)]
+    struct EnterExitLog(Arc<Mutex<Vec<&'static str>>>);
+
+    #[cfg(feature = "registry")]
+    impl EnterExitLog {
+        fn events(&self) -> Vec<&'static str> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    #[cfg(feature = "registry")]
+    struct EnterExitRecorder {
+        log: EnterExitLog,
+    }
+
+    #[cfg(feature = "registry")]
+    impl<C: Collect> Subscribe<C> for EnterExitRecorder {
+        fn on_enter(&self, _id: &span::Id, _ctx: Context<'_, C>) {
+            self.log.0.lock().unwrap().push("enter");
+        }
+
+        fn on_exit(&self, _id: &span::Id, _ctx: Context<'_, C>) {
+            self.log.0.lock().unwrap().push("exit");
+        }
+    }
+
+    #[cfg(feature = "registry")]
+    #[test]
+    fn filtered_enter_exit_survive_an_intervening_event() {
+        let log = EnterExitLog::default();
+        let toggle = ToggleFilter::new(true);
+        let filtered = EnterExitRecorder { log: log.clone() }.with_filter(toggle.clone());
+        let collector = filtered.with_collector(Registry::default());
+
+        tracing::collect::with_default(collector, || {
+            let span = tracing::info_span!("a");
+
+            // An intervening event, evaluated on the same thread between
+            // the span's creation and its `enter()`/`exit()`. Before the
+            // per-span `FilterMap` fix, `on_enter`/`on_exit` read the live
+            // `FILTERING` thread-local, which this event's own `enabled()`
+            // call clobbers for every other span on the thread -- so
+            // `"a"`'s `enter`/`exit` would be wrongly suppressed even
+            // though the filter was enabled when `"a"` was created.
+            toggle.set(false);
+            tracing::info!("noise");
+            toggle.set(true);
+
+            span.in_scope(|| {});
+        });
+
+        assert_eq!(log.events(), vec!["enter", "exit"]);
+    }
+}